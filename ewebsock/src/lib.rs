@@ -44,13 +44,38 @@ pub enum WsMessage {
 
     /// Only for native.
     Pong(Vec<u8>),
+
+    /// Close the connection, optionally with a given close code and reason.
+    ///
+    /// Sending this (e.g. via [`WsSender::close_with`]) performs a graceful
+    /// WebSocket close handshake instead of just dropping the connection.
+    Close(Option<CloseFrame>),
+}
+
+/// The code and reason given when a WebSocket connection is closed.
+///
+/// See [RFC 6455 §7.4](https://datatracker.ietf.org/doc/html/rfc6455#section-7.4)
+/// for the meaning of the status codes (e.g. `1000` for a normal closure,
+/// `1001` for going away).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseFrame {
+    /// The close status code.
+    pub code: u16,
+
+    /// The reason for closing, if any.
+    pub reason: String,
 }
 
 /// Something happening with the connection.
 #[derive(Clone, Debug)]
 pub enum WsEvent {
     /// The connection has been established, and you can start sending messages.
-    Opened,
+    Opened {
+        /// The subprotocol the server selected, if any (see [`Options::subprotocols`]).
+        ///
+        /// Always `None` on web, where subprotocol negotiation isn't exposed.
+        protocol: Option<String>,
+    },
 
     /// A message has been received.
     Message(WsMessage),
@@ -58,8 +83,8 @@ pub enum WsEvent {
     /// An error occurred.
     Error(String),
 
-    /// The connection has been closed.
-    Closed,
+    /// The connection has been closed, optionally with the peer's close code and reason.
+    Closed(Option<CloseFrame>),
 }
 
 
@@ -72,8 +97,42 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 pub(crate) type EventHandler = Box<dyn Send + Fn(WsEvent) -> ControlFlow<()>>;
 
+/// A custom TLS connector for `wss://` connections, e.g. to pin a custom
+/// root certificate store, enable mutual TLS, or pick a specific TLS backend.
+///
+/// Only available natively, and only when the `tls-rustls` or `tls-native`
+/// feature is enabled.
+#[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+#[derive(Clone)]
+pub enum Connector {
+    /// Use [`rustls`](https://docs.rs/rustls) with the given client configuration.
+    #[cfg(feature = "tls-rustls")]
+    Rustls(std::sync::Arc<rustls::ClientConfig>),
+
+    /// Use [`native-tls`](https://docs.rs/native-tls) with the given connector.
+    #[cfg(feature = "tls-native")]
+    NativeTls(native_tls::TlsConnector),
+}
+
+#[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+impl std::fmt::Debug for Connector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "tls-rustls")]
+            Self::Rustls(_) => f.write_str("Connector::Rustls(..)"),
+
+            #[cfg(feature = "tls-native")]
+            Self::NativeTls(_) => f.write_str("Connector::NativeTls(..)"),
+        }
+    }
+}
+
 /// Options for a connection.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    not(any(feature = "tls-rustls", feature = "tls-native")),
+    derive(PartialEq, Eq)
+)]
 pub struct Options {
     /// The maximum size of a single incoming message frame, in bytes.
     ///
@@ -85,6 +144,46 @@ pub struct Options {
 
     /// Delay blocking in ms - default 10ms
     pub delay_blocking: std::time::Duration,
+
+    /// Additional HTTP headers to send with the handshake request,
+    /// e.g. `Authorization`, cookies, or `Origin`.
+    ///
+    /// Ignored on Web, where the browser controls the handshake headers.
+    pub additional_headers: Vec<(String, String)>,
+
+    /// Subprotocols to request via `Sec-WebSocket-Protocol`, in preference order.
+    ///
+    /// The subprotocol the server picked (if any) is reported back in
+    /// [`WsEvent::Opened`].
+    ///
+    /// Ignored on Web, where the browser controls subprotocol negotiation.
+    pub subprotocols: Vec<String>,
+
+    /// Custom TLS connector to use for `wss://` URLs.
+    ///
+    /// When `None`, the native system certificate store is used.
+    ///
+    /// Only available natively, and only when the `tls-rustls` or `tls-native`
+    /// feature is enabled. Ignored on Web, where the browser manages TLS.
+    #[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+    pub tls_connector: Option<Connector>,
+
+    /// If set, a `Ping` is sent on this interval to keep the connection alive
+    /// and detect a silently dead peer.
+    ///
+    /// A zero interval is rejected: a [`WsEvent::Error`] is emitted and no
+    /// keepalive pings are sent.
+    ///
+    /// Ignored on Web.
+    pub keepalive_interval: Option<std::time::Duration>,
+
+    /// How long to wait for a `Pong` after a keepalive `Ping` before giving up
+    /// on the connection, emitting [`WsEvent::Error`] followed by [`WsEvent::Closed`].
+    ///
+    /// Only takes effect when [`Self::keepalive_interval`] is also set.
+    ///
+    /// Ignored on Web.
+    pub keepalive_timeout: Option<std::time::Duration>,
 }
 
 impl Default for Options {
@@ -92,6 +191,12 @@ impl Default for Options {
         Self {
             max_incoming_frame_size: 64 * 1024 * 1024,
             delay_blocking: std::time::Duration::from_millis(10),
+            additional_headers: Vec::new(),
+            subprotocols: Vec::new(),
+            #[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+            tls_connector: None,
+            keepalive_interval: None,
+            keepalive_timeout: None,
         }
     }
 }
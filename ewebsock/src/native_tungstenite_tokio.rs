@@ -1,12 +1,26 @@
 use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
-use crate::{EventHandler, Options, Result, WsEvent, WsMessage};
+use tokio_util::sync::PollSender;
+
+use crate::{CloseFrame, EventHandler, Options, Result, WsEvent, WsMessage};
 
 /// This is how you send [`WsMessage`]s to the server.
 ///
 /// When this is dropped, the connection is closed.
+///
+/// This also implements [`futures::Sink`], so it can be used with
+/// `split` and other `futures` combinators. `poll_ready` reflects the
+/// real backpressure of the underlying channel, but `poll_flush` only
+/// confirms handoff to that channel, not that the message has actually
+/// reached the socket.
+///
+/// Note that [`WsReceiver`] yields [`WsEvent`], not [`WsMessage`], so you
+/// can't `forward` one directly into the other — map/filter_map the
+/// [`WsEvent::Message`] payloads out first.
 pub struct WsSender {
-    tx: Option<tokio::sync::mpsc::Sender<WsMessage>>,
+    tx: PollSender<WsMessage>,
 }
 
 impl Drop for WsSender {
@@ -20,7 +34,7 @@ impl WsSender {
     ///
     /// You have to wait for [`WsEvent::Opened`] before you can start sending messages.
     pub fn send(&mut self, msg: WsMessage) {
-        if let Some(tx) = self.tx.clone() {
+        if let Some(tx) = self.tx.get_ref().cloned() {
             tokio::spawn(async move { tx.send(msg).await });
         }
     }
@@ -29,20 +43,71 @@ impl WsSender {
     ///
     /// This is called automatically when the sender is dropped.
     pub fn close(&mut self) {
-        if self.tx.is_some() {
+        if !self.tx.is_closed() {
             log::debug!("Closing WebSocket");
         }
-        self.tx = None;
+        self.tx.close();
+    }
+
+    /// Gracefully close the connection with the given close code and reason.
+    ///
+    /// This sends a WebSocket Close frame to the server and then shuts down
+    /// the writer, instead of just dropping the connection.
+    pub fn close_with(&mut self, code: u16, reason: impl Into<String>) {
+        self.send(WsMessage::Close(Some(CloseFrame {
+            code,
+            reason: reason.into(),
+        })));
+        self.close();
     }
 
     /// Forget about this sender without closing the connection.
     pub fn forget(mut self) {
-        #[allow(clippy::mem_forget)] // intentional
-        std::mem::forget(self.tx.take());
+        if let Some(tx) = self.tx.get_ref() {
+            let tx = tx.clone();
+            #[allow(clippy::mem_forget)] // intentional
+            std::mem::forget(tx);
+        }
+        self.tx.close();
+    }
+}
+
+impl futures::Sink<WsMessage> for WsSender {
+    type Error = crate::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.tx
+            .poll_reserve(cx)
+            .map_err(|_err| "WebSocket connection closed".to_owned())
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: WsMessage) -> Result<()> {
+        self.tx
+            .send_item(item)
+            .map_err(|_err| "WebSocket connection closed".to_owned())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // This only reflects handoff to the internal channel, not delivery to
+        // the socket, so it's always ready. Callers that need a delivery
+        // guarantee can't get one from this sink.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.close();
+        Poll::Ready(Ok(()))
     }
 }
 
 /// Receiver for incoming [`WsEvent`]s.
+///
+/// This also implements [`futures::Stream`], as an alternative to
+/// [`Self::try_recv`] for use with `StreamExt` and codecs.
+///
+/// Note that this yields [`WsEvent`], not [`WsMessage`], so it can't be
+/// `forward`ed directly into a [`WsSender`] (which is a `Sink<WsMessage>`) —
+/// use `.filter_map` to pull out the [`WsEvent::Message`] payloads first.
 pub struct WsReceiver {
     rx: tokio::sync::mpsc::UnboundedReceiver<WsEvent>,
 }
@@ -76,23 +141,63 @@ impl WsReceiver {
     }
 }
 
+impl futures::Stream for WsReceiver {
+    type Item = WsEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
 async fn ws_connect_async(
     url: String,
     options: Options,
     outgoing_messages_stream: impl futures::Stream<Item = WsMessage>,
     on_event: EventHandler,
 ) {
+    use futures::SinkExt as _;
     use futures::StreamExt as _;
+    use tungstenite::client::IntoClientRequest as _;
+
+    let mut request = match url.into_client_request() {
+        Ok(request) => request,
+        Err(err) => {
+            on_event(WsEvent::Error(err.to_string()));
+            return;
+        }
+    };
+
+    if let Err(err) = add_headers(request.headers_mut(), &options) {
+        on_event(WsEvent::Error(err));
+        return;
+    }
+
+    #[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+    let connector = options.tls_connector.clone().map(to_tokio_tungstenite_connector);
+    let keepalive_interval = match options.keepalive_interval {
+        Some(interval) if interval.is_zero() => {
+            on_event(WsEvent::Error(
+                "Options::keepalive_interval must be greater than zero; keepalive disabled"
+                    .to_owned(),
+            ));
+            None
+        }
+        other => other,
+    };
+    let keepalive_timeout = options.keepalive_timeout;
 
     let config = tungstenite::protocol::WebSocketConfig::from(options);
     let disable_nagle = false; // God damn everyone who adds negations to the names of their variables
-    let (ws_stream, _response) = match tokio_tungstenite::connect_async_with_config(
-        url,
-        Some(config),
-        disable_nagle,
-    )
-    .await
-    {
+
+    #[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+    let connect_result =
+        tokio_tungstenite::connect_async_tls_with_config(request, Some(config), disable_nagle, connector)
+            .await;
+    #[cfg(not(any(feature = "tls-rustls", feature = "tls-native")))]
+    let connect_result =
+        tokio_tungstenite::connect_async_with_config(request, Some(config), disable_nagle).await;
+
+    let (ws_stream, response) = match connect_result {
         Ok(result) => result,
         Err(err) => {
             on_event(WsEvent::Error(err.to_string()));
@@ -102,52 +207,182 @@ async fn ws_connect_async(
 
     log::info!("WebSocket handshake has been successfully completed");
 
-    let control = on_event(WsEvent::Opened);
+    let protocol = response
+        .headers()
+        .get(tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    let (write, read) = ws_stream.split();
+    futures_util::pin_mut!(outgoing_messages_stream);
+    futures_util::pin_mut!(write);
+    futures_util::pin_mut!(read);
+
+    // Whether a Close frame has already been sent (by us) or received (and
+    // auto-replied to by tungstenite), so we never issue a second competing
+    // write once the socket is closing.
+    let mut close_sent = false;
+
+    let control = on_event(WsEvent::Opened { protocol });
     if control.is_break() {
-        log::warn!("ControlFlow::Break not implemented for the tungstenite tokio backend");
+        log::debug!("Closing WebSocket: on_event returned ControlFlow::Break");
+        if !close_sent {
+            let _ = write.send(tungstenite::protocol::Message::Close(None)).await;
+            close_sent = true;
+        }
+        let _ = write.close().await;
+        return;
     }
 
-    let (write, read) = ws_stream.split();
+    let mut ping_interval = keepalive_interval
+        .map(|interval| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval));
 
-    let writer = outgoing_messages_stream
-        .map(|ws_message| match ws_message {
-            WsMessage::Text(text) => tungstenite::protocol::Message::Text(text),
-            WsMessage::Binary(data) => tungstenite::protocol::Message::Binary(data),
-            WsMessage::Ping(data) => tungstenite::protocol::Message::Ping(data),
-            WsMessage::Pong(data) => tungstenite::protocol::Message::Pong(data),
-            WsMessage::Unknown(_) => panic!("You cannot send WsMessage::Unknown"),
-        })
-        .map(Ok)
-        .forward(write);
-
-    let reader = read.for_each(move |event| {
-        let control = match event {
-            Ok(message) => match message {
-                tungstenite::protocol::Message::Text(text) => {
-                    on_event(WsEvent::Message(WsMessage::Text(text)))
+    // The instant of the earliest keepalive `Ping` that hasn't yet been
+    // answered by a `Pong` (or any other traffic from the peer).
+    // `None` means no ping is currently awaiting a reply.
+    let mut pong_deadline = None;
+
+    loop {
+        let next_ping = async {
+            match &mut ping_interval {
+                Some(interval) => interval.tick().await,
+                None => std::future::pending().await,
+            }
+        };
+        let pong_timeout = async {
+            match (pong_deadline, keepalive_timeout) {
+                (Some(sent_at), Some(timeout)) => tokio::time::sleep_until(sent_at + timeout).await,
+                _ => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            ws_message = outgoing_messages_stream.next() => {
+                let Some(ws_message) = ws_message else { break };
+                let is_close = matches!(ws_message, WsMessage::Close(_));
+                let message = match ws_message {
+                    WsMessage::Text(text) => tungstenite::protocol::Message::Text(text),
+                    WsMessage::Binary(data) => tungstenite::protocol::Message::Binary(data),
+                    WsMessage::Ping(data) => tungstenite::protocol::Message::Ping(data),
+                    WsMessage::Pong(data) => tungstenite::protocol::Message::Pong(data),
+                    WsMessage::Close(frame) => tungstenite::protocol::Message::Close(frame.map(
+                        |frame| tungstenite::protocol::frame::CloseFrame {
+                            code: frame.code.into(),
+                            reason: frame.reason.into(),
+                        },
+                    )),
+                    WsMessage::Unknown(_) => panic!("You cannot send WsMessage::Unknown"),
+                };
+                if write.send(message).await.is_err() {
+                    break;
                 }
-                tungstenite::protocol::Message::Binary(data) => {
-                    on_event(WsEvent::Message(WsMessage::Binary(data)))
+                if is_close {
+                    close_sent = true;
                 }
-                tungstenite::protocol::Message::Ping(data) => {
-                    on_event(WsEvent::Message(WsMessage::Ping(data)))
+            }
+            () = next_ping => {
+                arm_pong_deadline(&mut pong_deadline, tokio::time::Instant::now());
+                if write.send(tungstenite::protocol::Message::Ping(Vec::new())).await.is_err() {
+                    break;
                 }
-                tungstenite::protocol::Message::Pong(data) => {
-                    on_event(WsEvent::Message(WsMessage::Pong(data)))
+            }
+            () = pong_timeout => {
+                if on_event(WsEvent::Error("keepalive timeout".to_owned())).is_continue() {
+                    on_event(WsEvent::Closed(None));
                 }
-                tungstenite::protocol::Message::Close(_) => on_event(WsEvent::Closed),
-                tungstenite::protocol::Message::Frame(_) => ControlFlow::Continue(()),
-            },
-            Err(err) => on_event(WsEvent::Error(err.to_string())),
-        };
-        if control.is_break() {
-            log::warn!("ControlFlow::Break not implemented for the tungstenite tokio backend");
+                break;
+            }
+            incoming = read.next() => {
+                let Some(incoming) = incoming else { break };
+                let control = match incoming {
+                    Ok(message) => {
+                        if !matches!(message, tungstenite::protocol::Message::Frame(_)) {
+                            // Any traffic from the peer, not just a `Pong`, counts as a sign of life.
+                            pong_deadline = None;
+                        }
+                        match message {
+                            tungstenite::protocol::Message::Text(text) => {
+                                on_event(WsEvent::Message(WsMessage::Text(text)))
+                            }
+                            tungstenite::protocol::Message::Binary(data) => {
+                                on_event(WsEvent::Message(WsMessage::Binary(data)))
+                            }
+                            tungstenite::protocol::Message::Ping(data) => {
+                                on_event(WsEvent::Message(WsMessage::Ping(data)))
+                            }
+                            tungstenite::protocol::Message::Pong(data) => {
+                                on_event(WsEvent::Message(WsMessage::Pong(data)))
+                            }
+                            tungstenite::protocol::Message::Close(frame) => {
+                                // tungstenite already auto-replies with its own Close frame
+                                // once it has read one from the peer.
+                                close_sent = true;
+                                on_event(WsEvent::Closed(frame.map(|frame| CloseFrame {
+                                    code: frame.code.into(),
+                                    reason: frame.reason.into_owned(),
+                                })))
+                            }
+                            tungstenite::protocol::Message::Frame(_) => ControlFlow::Continue(()),
+                        }
+                    }
+                    Err(err) => on_event(WsEvent::Error(err.to_string())),
+                };
+                if control.is_break() {
+                    log::debug!("Closing WebSocket: on_event returned ControlFlow::Break");
+                    if !close_sent {
+                        let _ = write.send(tungstenite::protocol::Message::Close(None)).await;
+                        close_sent = true;
+                    }
+                    break;
+                }
+            }
         }
-        async {}
-    });
+    }
 
-    futures_util::pin_mut!(reader, writer);
-    futures_util::future::select(reader, writer).await;
+    let _ = write.close().await;
+}
+
+/// Arms the pong-deadline only if one isn't already pending, so repeated
+/// keepalive pings sent to a still-unanswered peer don't keep pushing the
+/// timeout forward.
+fn arm_pong_deadline(pong_deadline: &mut Option<tokio::time::Instant>, now: tokio::time::Instant) {
+    pong_deadline.get_or_insert(now);
+}
+
+/// Injects [`Options::additional_headers`] and [`Options::subprotocols`] into the
+/// handshake request.
+fn add_headers(
+    headers: &mut tungstenite::http::HeaderMap,
+    options: &Options,
+) -> std::result::Result<(), String> {
+    for (name, value) in &options.additional_headers {
+        let header_name = tungstenite::http::HeaderName::try_from(name.as_str())
+            .map_err(|err| format!("invalid header name {name:?}: {err}"))?;
+        let header_value = tungstenite::http::HeaderValue::try_from(value.as_str())
+            .map_err(|err| format!("invalid header value for {name:?}: {err}"))?;
+        headers.append(header_name, header_value);
+    }
+
+    if !options.subprotocols.is_empty() {
+        let value = tungstenite::http::HeaderValue::try_from(options.subprotocols.join(", "))
+            .map_err(|err| format!("invalid subprotocols: {err}"))?;
+        headers.insert(tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL, value);
+    }
+
+    Ok(())
+}
+
+/// Converts our platform-agnostic [`crate::Connector`] into the
+/// `tokio-tungstenite` type it wraps.
+#[cfg(any(feature = "tls-rustls", feature = "tls-native"))]
+fn to_tokio_tungstenite_connector(connector: crate::Connector) -> tokio_tungstenite::Connector {
+    match connector {
+        #[cfg(feature = "tls-rustls")]
+        crate::Connector::Rustls(config) => tokio_tungstenite::Connector::Rustls(config),
+
+        #[cfg(feature = "tls-native")]
+        crate::Connector::NativeTls(connector) => tokio_tungstenite::Connector::NativeTls(connector),
+    }
 }
 
 #[allow(clippy::unnecessary_wraps)]
@@ -174,9 +409,34 @@ fn ws_connect_native(url: String, options: Options, on_event: EventHandler) -> W
         ws_connect_async(url.clone(), options, outgoing_messages_stream, on_event).await;
         log::debug!("WS connection finished.");
     });
-    WsSender { tx: Some(tx) }
+    WsSender {
+        tx: PollSender::new(tx),
+    }
 }
 
 pub(crate) fn ws_receive_impl(url: String, options: Options, on_event: EventHandler) -> Result<()> {
     ws_connect_impl(url, options, on_event).map(|sender| sender.forget())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::arm_pong_deadline;
+
+    #[test]
+    fn arm_pong_deadline_does_not_push_back_an_already_pending_deadline() {
+        let mut pong_deadline = None;
+        let first_ping = tokio::time::Instant::now();
+        arm_pong_deadline(&mut pong_deadline, first_ping);
+        assert_eq!(pong_deadline, Some(first_ping));
+
+        // A later, still-unanswered ping must not move the deadline forward.
+        let second_ping = first_ping + std::time::Duration::from_secs(1);
+        arm_pong_deadline(&mut pong_deadline, second_ping);
+        assert_eq!(pong_deadline, Some(first_ping));
+
+        // Once a Pong (or other traffic) clears the deadline, the next ping arms it again.
+        pong_deadline = None;
+        arm_pong_deadline(&mut pong_deadline, second_ping);
+        assert_eq!(pong_deadline, Some(second_ping));
+    }
+}
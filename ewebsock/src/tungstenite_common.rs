@@ -0,0 +1,10 @@
+use crate::Options;
+
+impl From<Options> for tungstenite::protocol::WebSocketConfig {
+    fn from(options: Options) -> Self {
+        Self {
+            max_frame_size: Some(options.max_incoming_frame_size),
+            ..Self::default()
+        }
+    }
+}